@@ -0,0 +1,318 @@
+//! A small unified-diff renderer, used to give `--check` (and `--diff`) actionable output instead
+//! of a bare "not synchronized" error.
+
+use crate::{MARKER_END, MARKER_START};
+
+const CONTEXT: usize = 3;
+
+enum DiffOp<'a> {
+  Equal(&'a str),
+  Removed(&'a str),
+  Added(&'a str),
+}
+
+/// Diff the synchronized region (between the `cargo-sync-readme` markers) of `old` and `new`,
+/// rendering a unified diff with `+`/`-` lines and a few lines of context, the way `git diff`
+/// would. Falls back to diffing the whole string if a marker is missing from either side.
+pub fn diff_synchronized_region(old: &str, new: &str, colored: bool) -> String {
+  match (synchronized_region(old), synchronized_region(new)) {
+    (Some(old_region), Some(new_region)) => render_unified_diff(old_region, new_region, colored),
+    _ => render_unified_diff(old, new, colored),
+  }
+}
+
+fn synchronized_region(s: &str) -> Option<&str> {
+  let start = s.find(MARKER_START)? + MARKER_START.len();
+  let end = s[start..].find(MARKER_END)? + start;
+  Some(s[start..end].trim_matches('\n'))
+}
+
+fn render_unified_diff(old: &str, new: &str, colored: bool) -> String {
+  let ops = diff_lines(old, new);
+  let mut out = String::from("--- README (current)\n+++ README (generated)\n");
+
+  for hunk in hunks(&ops) {
+    out.push_str(&render_hunk(&hunk, colored));
+  }
+
+  out
+}
+
+struct Hunk<'a> {
+  /// 1-based starting line number and line count, in the old and new file respectively, the way
+  /// a `@@ -l,s +l,s @@` header describes them.
+  old_start: usize,
+  old_len: usize,
+  new_start: usize,
+  new_len: usize,
+  ops: Vec<&'a DiffOp<'a>>,
+}
+
+/// Group diff ops into hunks, keeping at most `CONTEXT` lines of unchanged context around each
+/// run of changes, and dropping runs of `Equal` lines that are far from any change.
+fn hunks<'a>(ops: &'a [DiffOp<'a>]) -> Vec<Hunk<'a>> {
+  let mut hunks = Vec::new();
+  let mut current: Vec<(usize, &DiffOp)> = Vec::new();
+  let mut pending_context: Vec<(usize, &DiffOp)> = Vec::new();
+  let mut trailing_equal = 0;
+
+  // Running counts of how many old/new lines have been consumed so far, so each hunk can record
+  // the 1-based line numbers it starts and ends at.
+  let (mut old_count, mut new_count) = (0usize, 0usize);
+  let positions: Vec<(usize, usize)> = ops
+    .iter()
+    .map(|op| {
+      match op {
+        DiffOp::Equal(_) => {
+          old_count += 1;
+          new_count += 1;
+        }
+        DiffOp::Removed(_) => old_count += 1,
+        DiffOp::Added(_) => new_count += 1,
+      }
+      (old_count, new_count)
+    })
+    .collect();
+
+  for (idx, op) in ops.iter().enumerate() {
+    match op {
+      DiffOp::Equal(_) => {
+        if current.is_empty() {
+          pending_context.push((idx, op));
+          if pending_context.len() > CONTEXT {
+            pending_context.remove(0);
+          }
+          continue;
+        }
+
+        current.push((idx, op));
+        trailing_equal += 1;
+
+        if trailing_equal > CONTEXT * 2 {
+          let keep = current.len() - (trailing_equal - CONTEXT);
+          let mut discarded = current.split_off(keep);
+          hunks.push(finish_hunk(std::mem::take(&mut current), &positions));
+
+          // The equal lines beyond the trailing context we just kept are still candidates for
+          // the *next* hunk's leading context — seed pending_context with the tail of them
+          // (capped at CONTEXT, which discarded.len() always exceeds here) instead of throwing
+          // them away, or a short enough gap leaves the next hunk with little or no leading
+          // context at all.
+          discarded.drain(..discarded.len() - CONTEXT);
+          pending_context = discarded;
+          trailing_equal = 0;
+        }
+      }
+      _ => {
+        if current.is_empty() {
+          current.append(&mut pending_context);
+        }
+
+        current.push((idx, op));
+        trailing_equal = 0;
+      }
+    }
+  }
+
+  if current.iter().any(|(_, op)| !matches!(op, DiffOp::Equal(_))) {
+    current.truncate(current.len() - trailing_equal.saturating_sub(CONTEXT));
+    hunks.push(finish_hunk(current, &positions));
+  }
+
+  hunks
+}
+
+/// Turn a run of `(original index, op)` pairs into a [`Hunk`], looking up each end's line numbers
+/// in `positions` (the running old/new line counts after each op in the full `ops` slice).
+fn finish_hunk<'a>(
+  entries: Vec<(usize, &'a DiffOp<'a>)>,
+  positions: &[(usize, usize)],
+) -> Hunk<'a> {
+  let first_idx = entries.first().map(|(idx, _)| *idx).unwrap_or(0);
+  let last_idx = entries.last().map(|(idx, _)| *idx).unwrap_or(0);
+
+  let (old_before, new_before) = if first_idx == 0 {
+    (0, 0)
+  } else {
+    positions[first_idx - 1]
+  };
+  let (old_after, new_after) = positions
+    .get(last_idx)
+    .copied()
+    .unwrap_or((old_before, new_before));
+
+  Hunk {
+    old_start: old_before + 1,
+    old_len: old_after - old_before,
+    new_start: new_before + 1,
+    new_len: new_after - new_before,
+    ops: entries.into_iter().map(|(_, op)| op).collect(),
+  }
+}
+
+fn render_hunk(hunk: &Hunk, colored: bool) -> String {
+  let mut out = format!(
+    "@@ -{},{} +{},{} @@\n",
+    hunk.old_start, hunk.old_len, hunk.new_start, hunk.new_len
+  );
+
+  for op in &hunk.ops {
+    match op {
+      DiffOp::Equal(line) => out.push_str(&format!(" {}\n", line)),
+      DiffOp::Removed(line) => out.push_str(&colorize('-', line, colored)),
+      DiffOp::Added(line) => out.push_str(&colorize('+', line, colored)),
+    }
+  }
+
+  out
+}
+
+fn colorize(marker: char, line: &str, colored: bool) -> String {
+  if !colored {
+    return format!("{}{}\n", marker, line);
+  }
+
+  let color = if marker == '-' { "31" } else { "32" };
+  format!("\x1b[{}m{}{}\x1b[0m\n", color, marker, line)
+}
+
+/// Line-based diff via a classic longest-common-subsequence backtrace. Good enough for README-
+/// sized documents; not meant to scale to huge files.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffOp<'a>> {
+  let old_lines: Vec<&str> = old.lines().collect();
+  let new_lines: Vec<&str> = new.lines().collect();
+  let (n, m) = (old_lines.len(), new_lines.len());
+
+  let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+  for i in (0..n).rev() {
+    for j in (0..m).rev() {
+      lcs[i][j] = if old_lines[i] == new_lines[j] {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+
+  let mut ops = Vec::new();
+  let (mut i, mut j) = (0, 0);
+
+  while i < n && j < m {
+    if old_lines[i] == new_lines[j] {
+      ops.push(DiffOp::Equal(old_lines[i]));
+      i += 1;
+      j += 1;
+    } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+      ops.push(DiffOp::Removed(old_lines[i]));
+      i += 1;
+    } else {
+      ops.push(DiffOp::Added(new_lines[j]));
+      j += 1;
+    }
+  }
+
+  ops.extend(old_lines[i..].iter().copied().map(DiffOp::Removed));
+  ops.extend(new_lines[j..].iter().copied().map(DiffOp::Added));
+
+  ops
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn wrapped(body: &str) -> String {
+    format!("{}\n{}\n{}", MARKER_START, body, MARKER_END)
+  }
+
+  fn lines(n: usize) -> Vec<String> {
+    (1..=n).map(|i| format!("line{}", i)).collect()
+  }
+
+  fn diff(old: &[String], new: &[String]) -> String {
+    diff_synchronized_region(&wrapped(&old.join("\n")), &wrapped(&new.join("\n")), false)
+  }
+
+  #[test]
+  fn single_hunk_for_one_change() {
+    let old = lines(10);
+    let mut new = old.clone();
+    new[4] = "CHANGED".to_owned();
+
+    assert_eq!(
+      diff(&old, &new),
+      "--- README (current)\n+++ README (generated)\n\
+       @@ -2,7 +2,7 @@\n line2\n line3\n line4\n-line5\n+CHANGED\n line6\n line7\n line8\n"
+    );
+  }
+
+  #[test]
+  fn two_hunks_with_large_gap() {
+    let old = lines(30);
+    let mut new = old.clone();
+    new[1] = "CHANGED-2".to_owned();
+    new[24] = "CHANGED-25".to_owned();
+
+    assert_eq!(
+      diff(&old, &new),
+      "--- README (current)\n+++ README (generated)\n\
+       @@ -1,5 +1,5 @@\n line1\n-line2\n+CHANGED-2\n line3\n line4\n line5\n\
+       @@ -22,7 +22,7 @@\n line22\n line23\n line24\n-line25\n+CHANGED-25\n line26\n line27\n \
+       line28\n"
+    );
+  }
+
+  /// A 7-line gap (`2 * CONTEXT + 1`) between two changes is long enough to split into two
+  /// hunks, but short enough that naively truncating the first hunk's trailing context — rather
+  /// than recycling it — left the second hunk with no leading context at all.
+  #[test]
+  fn two_hunks_with_borderline_gap_still_get_full_leading_context() {
+    let old = lines(11);
+    let mut new = old.clone();
+    new[1] = "CHANGED-2".to_owned();
+    new[9] = "CHANGED-10".to_owned();
+
+    assert_eq!(
+      diff(&old, &new),
+      "--- README (current)\n+++ README (generated)\n\
+       @@ -1,5 +1,5 @@\n line1\n-line2\n+CHANGED-2\n line3\n line4\n line5\n\
+       @@ -7,5 +7,5 @@\n line7\n line8\n line9\n-line10\n+CHANGED-10\n line11\n"
+    );
+  }
+
+  #[test]
+  fn change_at_start_of_region_has_no_leading_context() {
+    let old = lines(10);
+    let mut new = old.clone();
+    new[0] = "CHANGED-1".to_owned();
+
+    assert_eq!(
+      diff(&old, &new),
+      "--- README (current)\n+++ README (generated)\n\
+       @@ -1,4 +1,4 @@\n-line1\n+CHANGED-1\n line2\n line3\n line4\n"
+    );
+  }
+
+  #[test]
+  fn change_at_end_of_region_has_no_trailing_context() {
+    let old = lines(10);
+    let mut new = old.clone();
+    new[9] = "CHANGED-10".to_owned();
+
+    assert_eq!(
+      diff(&old, &new),
+      "--- README (current)\n+++ README (generated)\n\
+       @@ -7,4 +7,4 @@\n line7\n line8\n line9\n-line10\n+CHANGED-10\n"
+    );
+  }
+
+  #[test]
+  fn falls_back_to_diffing_whole_string_without_markers() {
+    let diff = diff_synchronized_region("line1\nline2\nline3", "line1\nCHANGED\nline3", false);
+
+    assert_eq!(
+      diff,
+      "--- README (current)\n+++ README (generated)\n@@ -1,3 +1,3 @@\n line1\n-line2\n+CHANGED\n line3\n"
+    );
+  }
+}
@@ -0,0 +1,638 @@
+//! Core library behind the `cargo sync-readme` binary.
+//!
+//! This crate exposes the building blocks used by `src/main.rs`: finding and reading the
+//! `Cargo.toml` manifest, extracting the inner (`//!`) documentation from a crate's entry point,
+//! and splicing that documentation into a README file between the `cargo-sync-readme` markers.
+
+mod diff;
+mod template;
+
+pub use diff::diff_synchronized_region;
+pub use template::render_template;
+
+use regex::Regex;
+use std::{
+  fmt, fs,
+  io,
+  path::{Path, PathBuf},
+  str::FromStr,
+};
+use toml::Value;
+
+/// The start marker automatically inserted around synchronized documentation.
+pub const MARKER_START: &str = "<!-- cargo-sync-readme start -->";
+/// The end marker automatically inserted around synchronized documentation.
+pub const MARKER_END: &str = "<!-- cargo-sync-readme end -->";
+/// The anchor marker a user places in their README to select where documentation goes.
+pub const MARKER_ANCHOR: &str = "<!-- cargo-sync-readme -->";
+
+/// A parsed `Cargo.toml` manifest, along with the directory it lives in.
+#[derive(Debug)]
+pub struct Manifest {
+  dir: PathBuf,
+  toml: Value,
+}
+
+/// Possible errors that can happen while looking up a manifest.
+#[derive(Debug)]
+pub enum FindManifestError {
+  CannotFindManifest,
+  CannotReadManifest(io::Error),
+  TomlError(toml::de::Error),
+}
+
+impl fmt::Display for FindManifestError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FindManifestError::CannotFindManifest => {
+        f.write_str("cannot find a Cargo.toml in this directory or any of its parents")
+      }
+      FindManifestError::CannotReadManifest(ref e) => write!(f, "cannot read Cargo.toml: {}", e),
+      FindManifestError::TomlError(ref e) => write!(f, "cannot parse Cargo.toml: {}", e),
+    }
+  }
+}
+
+/// Where to read the inner documentation from when a crate has both a library and a binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreferDocFrom {
+  Bin,
+  Lib,
+}
+
+impl FromStr for PreferDocFrom {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "bin" => Ok(PreferDocFrom::Bin),
+      "lib" => Ok(PreferDocFrom::Lib),
+      _ => Err(format!("unknown value {:?}; expected \"bin\" or \"lib\"", s)),
+    }
+  }
+}
+
+impl Manifest {
+  /// Walk up from `pwd` looking for the closest `Cargo.toml`.
+  pub fn find_manifest(pwd: impl AsRef<Path>) -> Result<Self, FindManifestError> {
+    let mut dir = pwd.as_ref().to_owned();
+
+    loop {
+      let candidate = dir.join("Cargo.toml");
+
+      if candidate.is_file() {
+        let content =
+          fs::read_to_string(&candidate).map_err(FindManifestError::CannotReadManifest)?;
+        let toml = content.parse::<Value>().map_err(FindManifestError::TomlError)?;
+
+        return Ok(Manifest { dir, toml });
+      }
+
+      if !dir.pop() {
+        return Err(FindManifestError::CannotFindManifest);
+      }
+    }
+  }
+
+  /// Directory this manifest lives in.
+  pub fn dir(&self) -> &Path {
+    &self.dir
+  }
+
+  /// Name of the crate, as declared in `[package] name`.
+  pub fn crate_name(&self) -> Option<String> {
+    self
+      .toml
+      .get("package")?
+      .get("name")?
+      .as_str()
+      .map(Into::into)
+  }
+
+  /// Path to the README file, as declared in `[package] readme`, defaulting to `README.md`.
+  pub fn readme(&self) -> PathBuf {
+    let readme = self
+      .toml
+      .get("package")
+      .and_then(|package| package.get("readme"))
+      .and_then(Value::as_str)
+      .unwrap_or("README.md");
+
+    self.dir.join(readme)
+  }
+
+  /// License of the crate, as declared in `[package] license`.
+  pub fn license(&self) -> Option<String> {
+    self
+      .toml
+      .get("package")?
+      .get("license")?
+      .as_str()
+      .map(Into::into)
+  }
+
+  /// Version of the crate, as declared in `[package] version`.
+  pub fn version(&self) -> Option<String> {
+    self
+      .toml
+      .get("package")?
+      .get("version")?
+      .as_str()
+      .map(Into::into)
+  }
+
+  /// Path to the README template, as declared in `[package.metadata.sync-readme] template`.
+  pub fn template(&self) -> Option<PathBuf> {
+    let template = self
+      .toml
+      .get("package")?
+      .get("metadata")?
+      .get("sync-readme")?
+      .get("template")?
+      .as_str()?;
+
+    Some(self.dir.join(template))
+  }
+
+  /// Load the manifest that lives directly in `dir` (no walking up to parent directories).
+  ///
+  /// This is what workspace support uses to resolve each member's own `Cargo.toml`, as opposed
+  /// to [`Manifest::find_manifest`], which is meant for locating the manifest of the crate the
+  /// command is invoked from.
+  pub fn for_dir(dir: impl AsRef<Path>) -> Result<Self, FindManifestError> {
+    let dir = dir.as_ref().to_owned();
+    let candidate = dir.join("Cargo.toml");
+    let content =
+      fs::read_to_string(&candidate).map_err(FindManifestError::CannotReadManifest)?;
+    let toml = content.parse::<Value>().map_err(FindManifestError::TomlError)?;
+
+    Ok(Manifest { dir, toml })
+  }
+
+  /// Resolve the `[workspace] members` globs (if any) into the directory of each member crate.
+  ///
+  /// Each glob pattern is resolved relative to this manifest's directory. Entries that don't
+  /// contain a `Cargo.toml` are silently skipped, since glob patterns such as `crates/*` can
+  /// match non-crate directories.
+  pub fn workspace_members(&self) -> Vec<PathBuf> {
+    let patterns = self
+      .toml
+      .get("workspace")
+      .and_then(|workspace| workspace.get("members"))
+      .and_then(Value::as_array);
+
+    let patterns = match patterns {
+      Some(patterns) => patterns,
+      None => return Vec::new(),
+    };
+
+    let mut members = Vec::new();
+
+    for pattern in patterns.iter().filter_map(Value::as_str) {
+      let full_pattern = self.dir.join(pattern);
+      let full_pattern = full_pattern.to_string_lossy();
+
+      let entries = match glob::glob(&full_pattern) {
+        Ok(entries) => entries,
+        Err(e) => {
+          eprintln!("invalid workspace member glob {:?}: {}", pattern, e);
+          continue;
+        }
+      };
+
+      for entry in entries.flatten() {
+        if entry.join("Cargo.toml").is_file() {
+          members.push(entry);
+        }
+      }
+    }
+
+    members
+  }
+
+  /// Find the entry point (`src/lib.rs` or `src/main.rs`) to read the inner documentation from.
+  pub fn entry_point(&self, prefer_doc_from: Option<PreferDocFrom>) -> Option<PathBuf> {
+    let lib = self.dir.join("src/lib.rs");
+    let bin = self.dir.join("src/main.rs");
+
+    match prefer_doc_from {
+      Some(PreferDocFrom::Lib) => Some(lib).filter(|p| p.is_file()),
+      Some(PreferDocFrom::Bin) => Some(bin).filter(|p| p.is_file()),
+      None => {
+        if lib.is_file() {
+          Some(lib)
+        } else if bin.is_file() {
+          Some(bin)
+        } else {
+          None
+        }
+      }
+    }
+  }
+}
+
+/// A value along with any non-fatal warnings collected while producing it.
+#[derive(Debug)]
+pub struct WithWarnings<T> {
+  pub value: T,
+  pub warnings: Vec<String>,
+}
+
+impl<T> WithWarnings<T> {
+  fn new(value: T) -> Self {
+    WithWarnings {
+      value,
+      warnings: Vec::new(),
+    }
+  }
+}
+
+/// Errors that can happen while extracting or transforming documentation.
+#[derive(Debug)]
+pub enum TransformError {
+  CannotReadEntryPoint(PathBuf, io::Error),
+  CannotReadIncludedDoc(PathBuf, io::Error),
+  CannotReadReadme(PathBuf, io::Error),
+  CannotReadTemplate(PathBuf, io::Error),
+  MissingMarker,
+  MissingTemplateDocsPlaceholder,
+}
+
+impl fmt::Display for TransformError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TransformError::CannotReadEntryPoint(ref path, ref e) => {
+        write!(f, "cannot read entry point {}: {}", path.display(), e)
+      }
+      TransformError::CannotReadIncludedDoc(ref path, ref e) => {
+        write!(f, "cannot read included doc file {}: {}", path.display(), e)
+      }
+      TransformError::CannotReadReadme(ref path, ref e) => {
+        write!(f, "cannot read readme {}: {}", path.display(), e)
+      }
+      TransformError::CannotReadTemplate(ref path, ref e) => {
+        write!(f, "cannot read template {}: {}", path.display(), e)
+      }
+      TransformError::MissingMarker => write!(
+        f,
+        "missing {} marker in readme; please add it where you want the documentation to go",
+        MARKER_ANCHOR
+      ),
+      TransformError::MissingTemplateDocsPlaceholder => write!(
+        f,
+        "template does not contain a {{{{docs}}}} placeholder; please add it where you want the \
+         documentation to go"
+      ),
+    }
+  }
+}
+
+/// Extract the inner documentation of a Rust source file.
+///
+/// This collects `//!` line comments as well as inner doc-attributes — `#![doc = "…"]` and
+/// `#![doc = include_str!("…")]` (resolved relative to `entry_point`) — merging them in source
+/// order so crates that assemble their front page out of a mix of both still produce a faithful
+/// README. Lines written behind `#![cfg_attr(feature = "…", doc = "…")]` are only included when
+/// `show_cfg_gated_doc` is set, since they usually don't apply unconditionally.
+///
+/// Rust code blocks are then normalized by [`normalize_rust_code_blocks`], so hidden lines and
+/// doctest-only fence attributes are handled consistently regardless of which of the above forms
+/// produced them. The returned string uses `\n` or `\r\n` line endings, depending on `crlf`.
+pub fn extract_inner_doc(
+  entry_point: impl AsRef<Path>,
+  show_hidden_doc: bool,
+  show_cfg_gated_doc: bool,
+  crlf: bool,
+) -> Result<String, TransformError> {
+  let entry_point = entry_point.as_ref();
+  let content = fs::read_to_string(entry_point)
+    .map_err(|e| TransformError::CannotReadEntryPoint(entry_point.to_owned(), e))?;
+  let base_dir = entry_point.parent().unwrap_or_else(|| Path::new("."));
+
+  let doc_attr_re = Regex::new(r#"^#!\[\s*doc\s*=\s*"((?:[^"\\]|\\.)*)"\s*\]$"#).unwrap();
+  let doc_include_re =
+    Regex::new(r#"^#!\[\s*doc\s*=\s*include_str!\(\s*"([^"]+)"\s*\)\s*\]$"#).unwrap();
+  let cfg_doc_attr_re =
+    Regex::new(r#"^#!\[\s*cfg_attr\(.*,\s*doc\s*=\s*"((?:[^"\\]|\\.)*)"\s*\)\s*\]$"#).unwrap();
+  let cfg_doc_include_re = Regex::new(
+    r#"^#!\[\s*cfg_attr\(.*,\s*doc\s*=\s*include_str!\(\s*"([^"]+)"\s*\)\s*\)\s*\]$"#,
+  )
+  .unwrap();
+
+  let mut lines = Vec::new();
+
+  for line in content.lines() {
+    let line = line.trim_start();
+
+    if let Some(rest) = line.strip_prefix("//!") {
+      let doc_line = rest.strip_prefix(' ').unwrap_or(rest);
+      lines.push(doc_line.to_owned());
+    } else if let Some(caps) = doc_include_re.captures(line) {
+      let included = read_included_doc(base_dir, &caps[1])?;
+      lines.extend(included.lines().map(str::to_owned));
+    } else if let Some(caps) = doc_attr_re.captures(line) {
+      lines.push(unescape_doc_attr(&caps[1]));
+    } else if show_cfg_gated_doc {
+      if let Some(caps) = cfg_doc_include_re.captures(line) {
+        let included = read_included_doc(base_dir, &caps[1])?;
+        lines.extend(included.lines().map(str::to_owned));
+      } else if let Some(caps) = cfg_doc_attr_re.captures(line) {
+        lines.push(unescape_doc_attr(&caps[1]));
+      }
+    }
+  }
+
+  let doc = normalize_rust_code_blocks(&lines.join("\n"), show_hidden_doc);
+
+  if crlf {
+    Ok(doc.replace('\n', "\r\n"))
+  } else {
+    Ok(doc)
+  }
+}
+
+/// Rustdoc-aware normalization of fenced Rust code blocks, so the README matches what docs.rs
+/// would show.
+///
+/// Within a fence whose info string is empty or mentions `rust`, `ignore`, `no_run`,
+/// `should_panic`, `compile_fail`, or an `edition20xx`-style attribute (all of which rustdoc
+/// treats as Rust code), this:
+///
+/// - rewrites the fence's info string to a plain `rust`, since those attributes are meaningful to
+///   doctests but render as noise on GitHub;
+/// - drops hidden (`# `-prefixed) lines, or un-hides them (stripping the `# `) when
+///   `show_hidden_doc` is set;
+/// - un-escapes the `##` → `#` doctest escape for a literal leading `#`.
+///
+/// Fence tracking respects backtick/tilde fence length, so nested or differently-fenced blocks
+/// and indented code or text outside of code blocks are left untouched.
+fn normalize_rust_code_blocks(doc: &str, show_hidden_doc: bool) -> String {
+  let fence_re = Regex::new(r"^(\s*)(`{3,}|~{3,})\s*(.*)$").unwrap();
+  let mut out = Vec::new();
+  let mut fence: Option<(char, usize)> = None;
+  let mut in_rust_fence = false;
+
+  for line in doc.lines() {
+    match fence {
+      None => {
+        if let Some(caps) = fence_re.captures(line) {
+          let indent = &caps[1];
+          let marker = &caps[2];
+          let info = &caps[3];
+          let fence_char = marker.chars().next().unwrap();
+
+          in_rust_fence = is_rust_info_string(info);
+          fence = Some((fence_char, marker.len()));
+
+          if in_rust_fence {
+            out.push(format!("{}{}rust", indent, marker));
+          } else {
+            out.push(line.to_owned());
+          }
+        } else {
+          out.push(line.to_owned());
+        }
+      }
+      Some((fence_char, fence_len)) => {
+        if is_fence_close(line, fence_char, fence_len) {
+          out.push(line.to_owned());
+          fence = None;
+          continue;
+        }
+
+        if !in_rust_fence {
+          out.push(line.to_owned());
+          continue;
+        }
+
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+
+        if let Some(rest) = trimmed.strip_prefix("##") {
+          out.push(format!("{}#{}", indent, rest));
+        } else if trimmed == "#" {
+          if show_hidden_doc {
+            out.push(indent.to_owned());
+          }
+        } else if let Some(rest) = trimmed.strip_prefix("# ") {
+          if show_hidden_doc {
+            out.push(format!("{}{}", indent, rest));
+          }
+        } else {
+          out.push(line.to_owned());
+        }
+      }
+    }
+  }
+
+  out.join("\n")
+}
+
+/// Whether a fenced code block's info string marks it as Rust code, the way rustdoc would treat
+/// it (a bare fence with no info string defaults to Rust).
+fn is_rust_info_string(info: &str) -> bool {
+  let info = info.trim();
+
+  if info.is_empty() {
+    return true;
+  }
+
+  info.split(',').map(str::trim).any(|attr| {
+    matches!(attr, "rust" | "ignore" | "no_run" | "should_panic" | "compile_fail")
+      || attr.starts_with("edition")
+  })
+}
+
+/// Whether `line` closes a fence opened with `fence_char` repeated (at least) `fence_len` times.
+///
+/// CommonMark (and rustdoc) allow trailing whitespace after a closing fence, so both ends are
+/// trimmed before checking that what's left is nothing but the fence character.
+fn is_fence_close(line: &str, fence_char: char, fence_len: usize) -> bool {
+  let trimmed = line.trim();
+  trimmed.chars().all(|c| c == fence_char) && trimmed.chars().count() >= fence_len
+}
+
+/// Resolve and read a file included via `include_str!("…")`, relative to the entry point's
+/// directory, the same way rustc would.
+fn read_included_doc(base_dir: &Path, rel_path: &str) -> Result<String, TransformError> {
+  let path = base_dir.join(rel_path);
+  fs::read_to_string(&path).map_err(|e| TransformError::CannotReadIncludedDoc(path, e))
+}
+
+/// Unescape the string-literal escapes (`\"`, `\\`, `\n`) that can appear in a `#![doc = "…"]`
+/// attribute's literal.
+fn unescape_doc_attr(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  let mut chars = s.chars();
+
+  while let Some(c) = chars.next() {
+    if c == '\\' {
+      match chars.next() {
+        Some('n') => out.push('\n'),
+        Some('t') => out.push('\t'),
+        Some(other) => out.push(other),
+        None => out.push('\\'),
+      }
+    } else {
+      out.push(c);
+    }
+  }
+
+  out
+}
+
+/// Read a README file from disk.
+pub fn read_readme(path: impl AsRef<Path>) -> Result<String, TransformError> {
+  let path = path.as_ref();
+  fs::read_to_string(path).map_err(|e| TransformError::CannotReadReadme(path.to_owned(), e))
+}
+
+/// Read a README template from disk.
+pub fn read_template(path: impl AsRef<Path>) -> Result<String, TransformError> {
+  let path = path.as_ref();
+  fs::read_to_string(path).map_err(|e| TransformError::CannotReadTemplate(path.to_owned(), e))
+}
+
+/// Splice freshly extracted documentation into a README, between the synchronized markers.
+///
+/// Intra-links of the form `[⋯](crate::⋯)` (or `[⋯](::std::⋯)`, `[⋯](::core::⋯)`,
+/// `[⋯](::alloc::⋯)`) are rewritten to point at the corresponding page on docs.rs. The Markdown
+/// shortcut-reference form (`[foo]` in prose, with `[foo]: crate::foo` defined elsewhere in the
+/// doc) is supported too: see [`rewrite_reference_definitions`].
+pub fn transform_readme(
+  readme: &str,
+  doc: String,
+  crate_name: String,
+  _entry_point: impl AsRef<Path>,
+  crlf: bool,
+) -> Result<WithWarnings<String>, TransformError> {
+  let WithWarnings {
+    value: doc,
+    warnings,
+  } = rewrite_links(&doc, &crate_name);
+
+  let nl = if crlf { "\r\n" } else { "\n" };
+  let synced = format!("{}{}{}{}{}", MARKER_START, nl, doc, nl, MARKER_END);
+
+  let new_readme = if let (Some(start), Some(end)) =
+    (readme.find(MARKER_START), readme.find(MARKER_END))
+  {
+    let end = end + MARKER_END.len();
+    format!("{}{}{}", &readme[..start], synced, &readme[end..])
+  } else if let Some(anchor) = readme.find(MARKER_ANCHOR) {
+    let end = anchor + MARKER_ANCHOR.len();
+    format!("{}{}{}", &readme[..anchor], synced, &readme[end..])
+  } else {
+    return Err(TransformError::MissingMarker);
+  };
+
+  Ok(WithWarnings::new(new_readme).with_warnings(warnings))
+}
+
+impl<T> WithWarnings<T> {
+  fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+    self.warnings = warnings;
+    self
+  }
+}
+
+/// Run every intra-link rewriting pass (inline links, then reference-definition links) over a
+/// piece of documentation. Shared by [`transform_readme`] and template rendering.
+pub(crate) fn rewrite_links(doc: &str, crate_name: &str) -> WithWarnings<String> {
+  let mut warnings = Vec::new();
+  let doc = rewrite_intra_links(doc, crate_name, &mut warnings);
+  let doc = rewrite_reference_definitions(&doc, crate_name, &mut warnings);
+
+  WithWarnings { value: doc, warnings }
+}
+
+fn docs_rs_url(crate_name: &str, path: &str) -> String {
+  format!("https://docs.rs/{}/*/{}", crate_name, path.replace("::", "/"))
+}
+
+fn rewrite_intra_links(doc: &str, crate_name: &str, warnings: &mut Vec<String>) -> String {
+  let re = Regex::new(r"\[([^\]]*)\]\((crate|::std|::core|::alloc)((?:::\w+)*)\)").unwrap();
+
+  re.replace_all(doc, |caps: &regex::Captures| {
+    let text = &caps[1];
+    let root = &caps[2];
+    let path = &caps[3];
+
+    let (target_crate, rest) = if root == "crate" {
+      (crate_name.to_owned(), path.trim_start_matches("::").to_owned())
+    } else {
+      let std_crate = root.trim_start_matches("::");
+      (std_crate.to_owned(), path.trim_start_matches("::").to_owned())
+    };
+
+    if rest.is_empty() {
+      warnings.push(format!(
+        "link [{}]({}{}) does not point at a symbol; left as-is",
+        text, root, path
+      ));
+      format!("[{}]({}{})", text, root, path)
+    } else {
+      format!("[{}]({})", text, docs_rs_url(&target_crate, &rest))
+    }
+  })
+  .into_owned()
+}
+
+/// Rewrite Markdown link-reference definitions (`[label]: crate::foo`) whose target is an
+/// intra-link, so they point at the corresponding docs.rs page.
+///
+/// This covers the shortcut reference form used in prose as plain `[foo]`, as well as the
+/// collapsed (`[foo][]`) and full (`[bar][foo]`) reference forms — none of those usages need to
+/// change, since they refer to the label by name; only the `[label]: target` definition itself is
+/// rewritten here. A definition whose target doesn't point past the crate root (e.g.
+/// `[foo]: crate`) is left as-is, with a warning.
+fn rewrite_reference_definitions(doc: &str, crate_name: &str, warnings: &mut Vec<String>) -> String {
+  let re = Regex::new(
+    r#"(?m)^(?P<indent> {0,3}\[[^\]]+\]:\s*)(?P<root>crate|::std|::core|::alloc)(?P<path>(?:::\w+)*)(?P<suffix>\s*(?:"[^"]*"|'[^']*'|\([^)]*\))?\s*)$"#,
+  )
+  .unwrap();
+
+  re.replace_all(doc, |caps: &regex::Captures| {
+    let indent = &caps["indent"];
+    let root = &caps["root"];
+    let path = &caps["path"];
+    let suffix = &caps["suffix"];
+
+    let target_crate = if root == "crate" {
+      crate_name.to_owned()
+    } else {
+      root.trim_start_matches("::").to_owned()
+    };
+    let rest = path.trim_start_matches("::").to_owned();
+
+    if rest.is_empty() {
+      warnings.push(format!(
+        "reference link definition {}{}{} does not point at a symbol; left as-is",
+        indent.trim_end(),
+        root,
+        path
+      ));
+      format!("{}{}{}{}", indent, root, path, suffix)
+    } else {
+      format!("{}{}{}", indent, docs_rs_url(&target_crate, &rest), suffix)
+    }
+  })
+  .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fence_with_trailing_whitespace_still_closes() {
+    // A closing fence followed by trailing whitespace (allowed by CommonMark/rustdoc) must still
+    // reset the fence state, or a later `# Heading` gets mistaken for a hidden doctest line.
+    let doc = "```\nfn f() {}\n```   \n# Heading after code block\nmore text";
+
+    assert_eq!(
+      normalize_rust_code_blocks(doc, false),
+      "```rust\nfn f() {}\n```   \n# Heading after code block\nmore text"
+    );
+  }
+}
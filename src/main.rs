@@ -60,8 +60,10 @@
 //! This tool rewrites intra-links so they point at the corresponding place in
 //! [docs.rs](https://docs.rs). The intra-links must be of the form `[⋯](crate::⋯)`.
 //!
-//! The regular shortcut notation (using `[foo]: crate::foo` at the end of your Markdown document
-//! and using `[foo]` everywhere else) is not currently supported.
+//! The regular shortcut-reference notation is also supported: define `[foo]: crate::foo` once
+//! (anywhere in the doc) and use `[foo]` everywhere else in prose. The collapsed (`[foo][]`) and
+//! full (`[bar][foo]`) reference forms work the same way, since only the `[foo]: ⋯` definition
+//! needs rewriting.
 //!
 //! Links to the standard library are also supported, and they must be of the form
 //! `[⋯](::<crate>::⋯)`, where `<crate>` is a crate that is part of the standard library, such as
@@ -91,21 +93,55 @@
 //!   the already present newlines but expect your document to be formatted with CRLF. If it’s
 //!   not then you will get punched in the face by a squirrel driving a motorcycle. Sorry. Also,
 //!   it will generate newlines with CRLF.
-//! - `-c --check`: check whether the *readme* is synchronized.
+//! - `-c --check`: check whether the *readme* is synchronized. On mismatch, a unified diff of
+//!   the synchronized region is printed to stderr.
+//! - `--diff`: print that same diff whenever the README would change, even outside of `--check`.
+//!   Colored output is used automatically when stderr is a terminal.
+//! - `--workspace`: synchronize the README of every workspace member instead of just the crate
+//!   in the current directory. Each member is processed independently, so one unsynchronized
+//!   member won’t stop the others from being processed — it only affects the final exit code,
+//!   which is handy when running `--check --workspace` in CI.
+//! - `-p` or `--package`: when used with `--workspace`, restrict synchronization to the given
+//!   package(s). May be passed several times.
+//! - `--show-cfg-gated-doc`: include documentation written behind
+//!   `#![cfg_attr(feature = "…", doc = "…")]` attributes, which is dropped by default since it
+//!   usually doesn’t apply unconditionally.
+//! - `--template`: render the README from a template file instead of splicing the documentation
+//!   into the existing *readme*. See the “README templates” section below.
+//!
+//! ## README templates
+//!
+//! If you’d rather keep a badge row or a hand-written license line above the generated
+//! documentation instead of maintaining it inside your *readme*, point `--template` (or the
+//! `template` key under `[package.metadata.sync-readme]` in your `Cargo.toml`) at a template
+//! file. It will be rendered into your *readme* file, substituting these placeholders:
+//!
+//! - `{{crate}}`: the crate’s name.
+//! - `{{license}}`: the crate’s `license`, as declared in `Cargo.toml`.
+//! - `{{version}}`: the crate’s `version`, as declared in `Cargo.toml`.
+//! - `{{docs}}`: the synchronized documentation, still wrapped in the usual
+//!   `<!-- cargo-sync-readme start/end -->` markers so re-running stays idempotent.
 //!
 //! ## Q/A and troubleshooting
 //!
 //! ### Are workspace crates supported?
 //!
-//! Not yet! If you have ideas how the tool should behave with them, please contribute with an issue or
-//! a PR!
+//! Yes! Pass `--workspace` to synchronize every member's README in one invocation, optionally
+//! narrowed down to specific packages with `-p`/`--package`.
 
-use std::{env::current_dir, fmt, fs::File, io::Write, process};
+use std::{
+  env::current_dir,
+  fmt,
+  fs::File,
+  io::{self, IsTerminal, Write},
+  path::PathBuf,
+  process,
+};
 use structopt::StructOpt;
 
 use cargo_sync_readme::{
-  extract_inner_doc, read_readme, transform_readme, FindManifestError, Manifest, PreferDocFrom,
-  TransformError, WithWarnings,
+  diff_synchronized_region, extract_inner_doc, read_readme, read_template, render_template,
+  transform_readme, FindManifestError, Manifest, PreferDocFrom, TransformError, WithWarnings,
 };
 
 #[derive(Debug, StructOpt)]
@@ -122,6 +158,12 @@ enum CliOpt {
     )]
     show_hidden_doc: bool,
 
+    #[structopt(
+      long,
+      help = "Include documentation written behind #![cfg_attr(feature = \"…\", doc = \"…\")] attributes."
+    )]
+    show_cfg_gated_doc: bool,
+
     #[structopt(
       short = "f",
       long,
@@ -137,6 +179,32 @@ enum CliOpt {
 
     #[structopt(short, long, help = "Check whether the README is synchronized.")]
     check: bool,
+
+    #[structopt(
+      long,
+      help = "Print a diff of what would change in the README, even outside of --check."
+    )]
+    diff: bool,
+
+    #[structopt(
+      long,
+      parse(from_os_str),
+      help = "Path to a README template, with {{crate}}, {{license}}, {{version}} and {{docs}} placeholders. Overrides [package.metadata.sync-readme] template."
+    )]
+    template: Option<PathBuf>,
+
+    #[structopt(
+      long,
+      help = "Synchronize the README of every workspace member instead of the current crate."
+    )]
+    workspace: bool,
+
+    #[structopt(
+      short = "p",
+      long = "package",
+      help = "Restrict --workspace to the given package(s). May be passed several times."
+    )]
+    package: Vec<String>,
   },
 }
 
@@ -206,27 +274,156 @@ fn main() {
   }
 }
 
+/// Flags shared by every crate we synchronize the README of, bundled up since a workspace run
+/// threads them through unchanged to each member.
+#[derive(Debug, Clone)]
+struct RunOpts {
+  prefer_doc_from: Option<PreferDocFrom>,
+  show_hidden_doc: bool,
+  show_cfg_gated_doc: bool,
+  crlf: bool,
+  check: bool,
+  diff: bool,
+  colored_diff: bool,
+  template: Option<PathBuf>,
+}
+
 fn run_with_manifest(manifest: Manifest, cli_opt: CliOpt) -> Result<(), RuntimeError> {
   let CliOpt::SyncReadme {
     prefer_doc_from,
     show_hidden_doc,
+    show_cfg_gated_doc,
     crlf,
     check,
-    ..
+    diff,
+    template,
+    workspace,
+    package,
   } = cli_opt;
 
+  let opts = RunOpts {
+    prefer_doc_from,
+    show_hidden_doc,
+    show_cfg_gated_doc,
+    crlf,
+    check,
+    diff,
+    colored_diff: io::stderr().is_terminal(),
+    template,
+  };
+
+  if workspace {
+    return run_workspace(manifest, opts, &package);
+  }
+
+  if !package.is_empty() {
+    return Err(RuntimeError::hard_error(
+      "-p/--package can only be used together with --workspace",
+    ));
+  }
+
+  run_single(manifest, opts)
+}
+
+/// Resolve and synchronize the README of every workspace member, aggregating warnings and
+/// reporting, at the end, which packages (if any) are out of sync. One unsynchronized member
+/// does not stop the others from being processed — it only affects the final exit status.
+fn run_workspace(manifest: Manifest, opts: RunOpts, package: &[String]) -> Result<(), RuntimeError> {
+  let members = manifest.workspace_members();
+
+  if members.is_empty() {
+    return Err(RuntimeError::hard_error(
+      "no workspace members found; is this really a workspace root Cargo.toml?",
+    ));
+  }
+
+  let mut out_of_sync = Vec::new();
+  let mut had_error = false;
+
+  for member_dir in members {
+    let member_manifest = match Manifest::for_dir(&member_dir) {
+      Ok(manifest) => manifest,
+      Err(e) => {
+        eprintln!("{}: {}", member_dir.display(), e);
+        had_error = true;
+        continue;
+      }
+    };
+
+    let member_name = match member_manifest.crate_name() {
+      Some(name) => name,
+      None => {
+        eprintln!("{}: failed to get the name of the crate", member_dir.display());
+        had_error = true;
+        continue;
+      }
+    };
+
+    if !package.is_empty() && !package.contains(&member_name) {
+      continue;
+    }
+
+    match run_single(member_manifest, opts.clone()) {
+      Ok(()) => (),
+      Err(RuntimeError::NotSynchronized) => out_of_sync.push(member_name),
+      Err(e) => {
+        eprintln!("{}: {}", member_name, e);
+        had_error = true;
+      }
+    }
+  }
+
+  if !out_of_sync.is_empty() {
+    eprintln!(
+      "the following package(s) are not synchronized: {}",
+      out_of_sync.join(", ")
+    );
+    Err(RuntimeError::NotSynchronized)
+  } else if had_error {
+    Err(RuntimeError::HadWarnings)
+  } else {
+    Ok(())
+  }
+}
+
+fn run_single(manifest: Manifest, opts: RunOpts) -> Result<(), RuntimeError> {
   let crate_name = manifest
     .crate_name()
     .ok_or_else(|| RuntimeError::hard_error("Failed to get the name of the crate"))?;
-  let entry_point = manifest.entry_point(prefer_doc_from);
+  let entry_point = manifest.entry_point(opts.prefer_doc_from);
 
   if let Some(entry_point) = entry_point {
-    let doc = extract_inner_doc(&entry_point, show_hidden_doc, crlf)?;
+    let doc = extract_inner_doc(
+      &entry_point,
+      opts.show_hidden_doc,
+      opts.show_cfg_gated_doc,
+      opts.crlf,
+    )?;
     let readme_path = manifest.readme();
-    let (old_readme, new_readme_with_warnings) = read_readme(&readme_path).and_then(|readme| {
-      transform_readme(&readme, doc, crate_name, entry_point, crlf)
-        .map(|new_readme_with_warnings| (readme, new_readme_with_warnings))
-    })?;
+    let template_path = opts.template.clone().or_else(|| manifest.template());
+
+    let (old_readme, new_readme_with_warnings) = if let Some(template_path) = template_path {
+      let template = read_template(&template_path)?;
+      // The rendered file may not exist yet, unlike the marker-splicing path below, which
+      // requires an existing README to locate the markers (or anchor) in.
+      let old_readme = read_readme(&readme_path).unwrap_or_default();
+      let rendered = render_template(
+        &template,
+        doc,
+        &crate_name,
+        manifest.license().as_deref(),
+        manifest.version().as_deref(),
+        opts.crlf,
+      )?;
+
+      (old_readme, rendered)
+    } else {
+      read_readme(&readme_path).and_then(|readme| {
+        transform_readme(&readme, doc, crate_name, entry_point, opts.crlf)
+          .map(|new_readme_with_warnings| (readme, new_readme_with_warnings))
+      })?
+    };
+
     let WithWarnings {
       value: new_readme,
       warnings,
@@ -236,9 +433,16 @@ fn run_with_manifest(manifest: Manifest, cli_opt: CliOpt) -> Result<(), RuntimeE
       eprintln!("{}", w);
     }
 
-    if check {
-      report_synchronized(&old_readme, &new_readme)
+    if opts.check {
+      report_synchronized(&old_readme, &new_readme, opts.colored_diff)
     } else {
+      if opts.diff && old_readme != new_readme {
+        eprintln!(
+          "{}",
+          diff_synchronized_region(&old_readme, &new_readme, opts.colored_diff)
+        );
+      }
+
       let mut file = File::create(readme_path).unwrap();
       let _ = file.write_all(new_readme.as_bytes());
 
@@ -253,8 +457,9 @@ fn run_with_manifest(manifest: Manifest, cli_opt: CliOpt) -> Result<(), RuntimeE
   }
 }
 
-fn report_synchronized(old: &str, new: &str) -> Result<(), RuntimeError> {
+fn report_synchronized(old: &str, new: &str, colored: bool) -> Result<(), RuntimeError> {
   if old != new {
+    eprintln!("{}", diff_synchronized_region(old, new, colored));
     Err(RuntimeError::NotSynchronized)
   } else {
     Ok(())
@@ -0,0 +1,45 @@
+//! Optional README templating, for crates that want auto-generated badges or a license line
+//! above the synchronized documentation instead of hand-maintaining them.
+
+use crate::{rewrite_links, TransformError, WithWarnings, MARKER_END, MARKER_START};
+
+/// Render a README template, substituting `{{crate}}`, `{{license}}`, `{{version}}`, and
+/// `{{docs}}` placeholders.
+///
+/// `{{docs}}` is substituted with the (link-rewritten) synchronized documentation, still
+/// bracketed by the usual `cargo-sync-readme` markers, so that re-running the template through
+/// this function again is idempotent and `--check` keeps working the same way it does without a
+/// template. A template missing the `{{docs}}` placeholder would otherwise silently render
+/// without any of the crate's documentation in it, so that's rejected the same way a README
+/// missing the marker is in [`transform_readme`](crate::transform_readme).
+pub fn render_template(
+  template: &str,
+  doc: String,
+  crate_name: &str,
+  license: Option<&str>,
+  version: Option<&str>,
+  crlf: bool,
+) -> Result<WithWarnings<String>, TransformError> {
+  if !template.contains("{{docs}}") {
+    return Err(TransformError::MissingTemplateDocsPlaceholder);
+  }
+
+  let WithWarnings {
+    value: doc,
+    warnings,
+  } = rewrite_links(&doc, crate_name);
+
+  let nl = if crlf { "\r\n" } else { "\n" };
+  let synced_docs = format!("{}{}{}{}{}", MARKER_START, nl, doc, nl, MARKER_END);
+
+  let rendered = template
+    .replace("{{crate}}", crate_name)
+    .replace("{{license}}", license.unwrap_or(""))
+    .replace("{{version}}", version.unwrap_or(""))
+    .replace("{{docs}}", &synced_docs);
+
+  Ok(WithWarnings {
+    value: rendered,
+    warnings,
+  })
+}